@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use anyhow::{anyhow, Context, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Local file the CLI seals private keys into. Never contains a raw secret.
+pub(crate) const KEYSTORE_PATH: &str = "keystore.json";
+
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreEntry {
+    account_id: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// An encrypted, alias-keyed store of account private keys. Secrets are
+/// sealed with AES-256-GCM under a key derived from a user passphrase via
+/// scrypt; the raw key never touches disk.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Keystore {
+    entries: HashMap<String, KeystoreEntry>,
+}
+
+impl Keystore {
+    pub(crate) fn load() -> Result<Self> {
+        if !Path::new(KEYSTORE_PATH).exists() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read_to_string(KEYSTORE_PATH).context("reading keystore file")?;
+        serde_json::from_str(&raw).context("parsing keystore file")
+    }
+
+    fn save(&self) -> Result<()> {
+        let raw = serde_json::to_string_pretty(self).context("serializing keystore")?;
+        fs::write(KEYSTORE_PATH, raw).context("writing keystore file")
+    }
+
+    pub(crate) fn aliases(&self) -> Vec<(&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(alias, entry)| (alias.as_str(), entry.account_id.as_str()))
+            .collect()
+    }
+
+    pub(crate) fn seal(&mut self, alias: &str, account_id: &[u8], secret_key: &[u8], passphrase: &str) -> Result<()> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key_bytes = derive_key(passphrase, &salt)?;
+
+        let mut nonce_bytes = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), secret_key)
+            .map_err(|_| anyhow!("failed to seal private key"))?;
+
+        self.entries.insert(
+            alias.to_string(),
+            KeystoreEntry {
+                account_id: hex::encode(account_id),
+                salt: hex::encode(salt),
+                nonce: hex::encode(nonce_bytes),
+                ciphertext: hex::encode(ciphertext),
+            },
+        );
+
+        self.save()
+    }
+
+    pub(crate) fn unlock(&self, alias: &str, passphrase: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(alias)
+            .with_context(|| format!("no such alias in keystore: {alias}"))?;
+
+        let salt = hex::decode(&entry.salt).context("corrupt keystore entry: invalid salt")?;
+        let nonce = hex::decode(&entry.nonce).context("corrupt keystore entry: invalid nonce")?;
+        let ciphertext = hex::decode(&entry.ciphertext).context("corrupt keystore entry: invalid ciphertext")?;
+
+        let key_bytes = derive_key(passphrase, &salt)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+
+        cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| anyhow!("wrong passphrase or corrupted keystore entry"))
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, 32)
+        .map_err(|_| anyhow!("invalid scrypt parameters"))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key).map_err(|_| anyhow!("key derivation failed"))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `Keystore::load`/`save` operate on the fixed relative path
+    // `KEYSTORE_PATH`, so tests that exercise them must serialize and run
+    // from a scratch directory (shared per process, with the keystore file
+    // removed on entry and exit) instead of racing on the process-wide
+    // current directory.
+    static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+    struct ScratchDir {
+        _guard: std::sync::MutexGuard<'static, ()>,
+        original: std::path::PathBuf,
+    }
+
+    impl ScratchDir {
+        fn enter() -> Self {
+            let guard = CWD_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let original = std::env::current_dir().expect("current dir");
+            let dir = std::env::temp_dir().join(format!("m10-keystore-test-{}", std::process::id()));
+            fs::create_dir_all(&dir).expect("create scratch dir");
+            std::env::set_current_dir(&dir).expect("enter scratch dir");
+            let _ = fs::remove_file(KEYSTORE_PATH);
+            Self { _guard: guard, original }
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(KEYSTORE_PATH);
+            let _ = std::env::set_current_dir(&self.original);
+        }
+    }
+
+    #[test]
+    fn seal_then_unlock_round_trips_the_secret() {
+        let _scratch = ScratchDir::enter();
+
+        let mut keystore = Keystore::load().unwrap();
+        let secret = [7u8; 32];
+        keystore.seal("alias", &[1, 2, 3], &secret, "hunter2").unwrap();
+
+        let reloaded = Keystore::load().unwrap();
+        let unlocked = reloaded.unlock("alias", "hunter2").unwrap();
+        assert_eq!(unlocked, secret);
+    }
+
+    #[test]
+    fn unlock_rejects_the_wrong_passphrase() {
+        let _scratch = ScratchDir::enter();
+
+        let mut keystore = Keystore::load().unwrap();
+        keystore.seal("alias", &[1, 2, 3], &[7u8; 32], "hunter2").unwrap();
+
+        assert!(keystore.unlock("alias", "wrong").is_err());
+    }
+
+    #[test]
+    fn unlock_rejects_an_unknown_alias() {
+        let _scratch = ScratchDir::enter();
+
+        let keystore = Keystore::load().unwrap();
+        assert!(keystore.unlock("nope", "anything").is_err());
+    }
+}
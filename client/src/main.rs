@@ -1,14 +1,18 @@
+mod keystore;
+
 use std::{process::exit, u64};
 
 use anyhow::Result;
 use clap::Parser;
-use protos::{ledger_client::LedgerClient, Account, Action, CreateAccountReq, FreezeAccountRequest, GetAccountReq, 
+use protos::{ledger_client::LedgerClient, Account, Action, CreateAccountReq, FreezeAccountRequest, GetAccountReq,
     GetHistoryRequest, Transfer, UnfreezeAccountRequest};
 use protos::action::ActionType;
 use hex;
+use keystore::Keystore;
 use secp256k1::{SecretKey, Secp256k1, Message};
 use sha2::{Sha256, Digest};
 use rand::Rng;
+use tonic::Code;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -22,6 +26,15 @@ enum Cmd {
     Create {
         name: String,
         balance: u64,
+        /// Number of decimal places the balance is denominated in.
+        #[arg(default_value_t = 0)]
+        decimals: u32,
+        /// Optional asset/denom label, e.g. "USD" or "BTC".
+        #[arg(default_value = "")]
+        asset: String,
+        /// Rolling 24h withdrawal limit, in whole-token units. 0 means unlimited.
+        #[arg(default_value_t = 0)]
+        limit: u64,
     },
     Get {
         id: String,
@@ -30,7 +43,7 @@ enum Cmd {
         from: String,
         to: String,
         amount: u64,
-        private_key: String,
+        alias: String,
     },
     Freeze {
         id: String,
@@ -41,25 +54,40 @@ enum Cmd {
     GetHistory {
         id: String,
         limit: Option<u64>
-    }
+    },
+    /// Decrypt and print the private key stored under an alias.
+    Unlock {
+        alias: String,
+    },
+    /// List the account aliases held in the local keystore.
+    List,
 }
 
 
 impl Cmd {
     async fn exec(self) -> Result<()> {
-        let mut client = LedgerClient::connect("http://localhost:50051").await?;
         match self {
-            Cmd::Create { name, balance } => {
+            Cmd::Create { name, balance, decimals, asset, limit } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let resp = client
-                    .create_account(CreateAccountReq { name, balance })
+                    .create_account(CreateAccountReq { name: name.clone(), balance, decimals, asset, limit })
                     .await?
                     .into_inner();
-                println!("id: {}", hex::encode(&resp.account.as_ref().unwrap().id));
-                println!("name: {}", resp.account.as_ref().unwrap().name);
-                println!("balance: {}", resp.account.as_ref().unwrap().balance);
-                println!("private_key: {}", hex::encode(resp.private_key));
+                let account = resp.account.as_ref().unwrap();
+                println!("id: {}", hex::encode(&account.id));
+                println!("name: {}", account.name);
+                println!("balance: {}", account.balance);
+                println!("decimals: {}", account.decimals);
+                println!("asset: {}", account.asset);
+                println!("limit: {}", account.limit);
+
+                let passphrase = rpassword::prompt_password("Set a passphrase to encrypt the new private key: ")?;
+                let mut keystore = Keystore::load()?;
+                keystore.seal(&name, &account.id, &resp.private_key, &passphrase)?;
+                println!("private key sealed under alias \"{name}\" in {}", keystore::KEYSTORE_PATH);
             }
             Cmd::Get { id } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let decoded_id = hex::decode(&id);
                 match decoded_id {
                     Ok(decoded) => {
@@ -74,7 +102,8 @@ impl Cmd {
                     }
                 }
             }
-            Cmd::Transfer { from, to, amount, private_key } => {
+            Cmd::Transfer { from, to, amount, alias } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let from_decoded = match hex::decode(&from) {
                     Ok(decoded) => decoded,
                     Err(_) => {
@@ -103,16 +132,13 @@ impl Cmd {
                 let secp = Secp256k1::new();
                 let message_hash = Sha256::digest(&message);
 
-                let secret_key = match hex::decode(&private_key) {
-                    Ok(decoded) => SecretKey::from_slice(&decoded).unwrap_or_else(|e| {
-                        println!("Error: Invalid private key: {}", e);
-                        exit(1);
-                    }),
-                    Err(err) => {
-                        println!("Error: Invalid private key format: {}", err);
-                        exit(1);
-                    }
-                };
+                let passphrase = rpassword::prompt_password("Passphrase: ")?;
+                let keystore = Keystore::load()?;
+                let secret_bytes = keystore.unlock(&alias, &passphrase)?;
+                let secret_key = SecretKey::from_slice(&secret_bytes).unwrap_or_else(|e| {
+                    println!("Error: Invalid private key in keystore: {}", e);
+                    exit(1);
+                });
 
                 let message_hash = Message::from_digest_slice(&message_hash)
                     .map_err(|e| {
@@ -137,6 +163,7 @@ impl Cmd {
             }
             
             Cmd::Freeze { id } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let decoded_id = hex::decode(&id);
                 match decoded_id {
                     Ok(decoded) => {
@@ -149,6 +176,7 @@ impl Cmd {
                 }
             }
             Cmd::Unfreeze { id } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let decoded_id = hex::decode(&id);
                 match decoded_id {
                     Ok(decoded) => {
@@ -161,19 +189,27 @@ impl Cmd {
                 }
             }
             Cmd::GetHistory { id, limit } => {
+                let mut client = LedgerClient::connect("http://localhost:50051").await?;
                 let decoded_id = hex::decode(&id);
                 match decoded_id {
                     Ok(decoded) => {
-                        let resp = client.get_history(GetHistoryRequest {
-                            id: decoded,
-                            limit: limit.unwrap_or(u64::MAX),
-                        }).await?.into_inner();
+                        let resp = match client
+                            .get_history(GetHistoryRequest { id: decoded, limit: limit.unwrap_or(u64::MAX) })
+                            .await
+                        {
+                            Ok(resp) => resp.into_inner(),
+                            Err(status) if status.code() == Code::NotFound => {
+                                println!("Account not found");
+                                return Ok(());
+                            }
+                            Err(status) => return Err(status.into()),
+                        };
 
-                        if resp.actions.len() < 1 {
-                            println!("Account not found");
+                        if resp.actions.is_empty() {
+                            println!("No history for this account");
                             return Ok(());
                         }
-                        
+
                         for (index, i) in resp.actions.iter().enumerate() {
                             println!("--------------------------");
                             println!("Index: {}", index + 1);
@@ -186,6 +222,24 @@ impl Cmd {
                     }
                 }
             }
+            Cmd::Unlock { alias } => {
+                let passphrase = rpassword::prompt_password("Passphrase: ")?;
+                let keystore = Keystore::load()?;
+                let secret = keystore.unlock(&alias, &passphrase)?;
+                println!("alias \"{alias}\" unlocked");
+                println!("private_key: {}", hex::encode(secret));
+            }
+            Cmd::List => {
+                let keystore = Keystore::load()?;
+                let aliases = keystore.aliases();
+                if aliases.is_empty() {
+                    println!("No aliases stored in {}", keystore::KEYSTORE_PATH);
+                    return Ok(());
+                }
+                for (alias, account_id) in aliases {
+                    println!("{alias}: {account_id}");
+                }
+            }
         }
         Ok(())
     }
@@ -215,6 +269,9 @@ fn display_account(account: &Account) {
     println!("    name: \"{}\"", account.name);
     println!("    balance: {}", account.balance);
     println!("    is_frozen: {}", account.is_frozen);
+    println!("    decimals: {}", account.decimals);
+    println!("    asset: \"{}\"", account.asset);
+    println!("    limit: {}", account.limit);
     println!("}}");
 }
 
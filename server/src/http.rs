@@ -0,0 +1,279 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use protos::action::ActionType;
+use protos::ledger_server::Ledger as _;
+use protos::{
+    transfer_error, Account, Action, CreateAccountReq, FreezeAccountRequest, GetAccountReq,
+    GetHistoryRequest, Transfer, TransferError, UnfreezeAccountRequest,
+};
+use serde::{Deserialize, Serialize};
+use tonic::{Request, Status};
+
+use crate::Ledger;
+
+/// Runs the JSON/REST mirror of the gRPC `Ledger` service on `addr`, sharing
+/// the same `Ledger` (and therefore the same store) as the gRPC server.
+pub async fn serve(ledger: Ledger, addr: SocketAddr) -> std::io::Result<()> {
+    let app = Router::new()
+        .route("/accounts", post(create_account))
+        .route("/accounts/:id", get(get_account))
+        .route("/accounts/:id/freeze", post(freeze_account))
+        .route("/accounts/:id/unfreeze", post(unfreeze_account))
+        .route("/accounts/:id/history", get(get_history))
+        .route("/transfers", post(create_transfer))
+        .with_state(ledger);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::BAD_REQUEST, message: message.into() }
+    }
+}
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> Self {
+        let code = match status.code() {
+            tonic::Code::NotFound => StatusCode::NOT_FOUND,
+            tonic::Code::InvalidArgument => StatusCode::BAD_REQUEST,
+            tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self { status: code, message: status.message().to_string() }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(serde_json::json!({ "error": self.message }))).into_response()
+    }
+}
+
+fn decode_id(raw: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(raw).map_err(|_| ApiError::bad_request("id must be valid hex"))
+}
+
+#[derive(Deserialize)]
+struct CreateAccountBody {
+    name: String,
+    balance: u64,
+    #[serde(default)]
+    decimals: u32,
+    #[serde(default)]
+    asset: String,
+    #[serde(default)]
+    limit: u64,
+}
+
+#[derive(Serialize)]
+struct CreateAccountResponseBody {
+    id: String,
+    name: String,
+    balance: u64,
+    decimals: u32,
+    asset: String,
+    limit: u64,
+    private_key: String,
+}
+
+async fn create_account(
+    State(ledger): State<Ledger>,
+    Json(body): Json<CreateAccountBody>,
+) -> Result<Json<CreateAccountResponseBody>, ApiError> {
+    let resp = ledger
+        .create_account(Request::new(CreateAccountReq {
+            name: body.name,
+            balance: body.balance,
+            decimals: body.decimals,
+            asset: body.asset,
+            limit: body.limit,
+        }))
+        .await?
+        .into_inner();
+    let account = resp.account.expect("create_account always returns an account");
+
+    Ok(Json(CreateAccountResponseBody {
+        id: hex::encode(&account.id),
+        name: account.name,
+        balance: account.balance,
+        decimals: account.decimals,
+        asset: account.asset,
+        limit: account.limit,
+        private_key: hex::encode(resp.private_key),
+    }))
+}
+
+#[derive(Serialize)]
+struct AccountBody {
+    id: String,
+    name: String,
+    balance: u64,
+    is_frozen: bool,
+    decimals: u32,
+    asset: String,
+    limit: u64,
+}
+
+impl From<Account> for AccountBody {
+    fn from(account: Account) -> Self {
+        Self {
+            id: hex::encode(&account.id),
+            name: account.name,
+            balance: account.balance,
+            is_frozen: account.is_frozen,
+            decimals: account.decimals,
+            asset: account.asset,
+            limit: account.limit,
+        }
+    }
+}
+
+async fn get_account(
+    State(ledger): State<Ledger>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountBody>, ApiError> {
+    let id = decode_id(&id)?;
+    let account = ledger.get_account(Request::new(GetAccountReq { id })).await?.into_inner();
+    Ok(Json(account.into()))
+}
+
+#[derive(Serialize)]
+struct FreezeResponseBody {
+    success: bool,
+    message: String,
+}
+
+async fn freeze_account(
+    State(ledger): State<Ledger>,
+    Path(id): Path<String>,
+) -> Result<Json<FreezeResponseBody>, ApiError> {
+    let id = decode_id(&id)?;
+    let resp = ledger.freeze_account(Request::new(FreezeAccountRequest { id })).await?.into_inner();
+    Ok(Json(FreezeResponseBody { success: resp.success, message: resp.message }))
+}
+
+async fn unfreeze_account(
+    State(ledger): State<Ledger>,
+    Path(id): Path<String>,
+) -> Result<Json<FreezeResponseBody>, ApiError> {
+    let id = decode_id(&id)?;
+    let resp = ledger.unfreeze_account(Request::new(UnfreezeAccountRequest { id })).await?.into_inner();
+    Ok(Json(FreezeResponseBody { success: resp.success, message: resp.message }))
+}
+
+#[derive(Deserialize)]
+struct TransferBody {
+    from: String,
+    to: String,
+    amount: u64,
+    signature: String,
+    nonce: u64,
+}
+
+#[derive(Serialize)]
+struct TransferResultBody {
+    error: Option<TransferErrorBody>,
+}
+
+#[derive(Serialize)]
+struct TransferErrorBody {
+    code: String,
+    message: String,
+}
+
+fn transfer_error_status(err: &TransferError) -> StatusCode {
+    use transfer_error::Code;
+    match Code::try_from(err.code).unwrap_or(Code::InvalidSignature) {
+        Code::FrozenAccount => StatusCode::FORBIDDEN,
+        Code::InvalidSignature => StatusCode::UNAUTHORIZED,
+        Code::InsufficientBalance => StatusCode::PAYMENT_REQUIRED,
+        Code::BalanceOverflow => StatusCode::UNPROCESSABLE_ENTITY,
+        Code::AccountNotFound => StatusCode::NOT_FOUND,
+        Code::ReplayedNonce => StatusCode::CONFLICT,
+        Code::LimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+    }
+}
+
+fn transfer_error_code_name(code: i32) -> String {
+    transfer_error::Code::try_from(code)
+        .map(|c| format!("{c:?}"))
+        .unwrap_or_else(|_| "Unknown".to_string())
+}
+
+async fn create_transfer(
+    State(ledger): State<Ledger>,
+    Json(body): Json<TransferBody>,
+) -> Result<(StatusCode, Json<TransferResultBody>), ApiError> {
+    let transfer = Transfer {
+        from_account: decode_id(&body.from)?,
+        to_account: decode_id(&body.to)?,
+        amount: body.amount,
+        signature: hex::decode(&body.signature).map_err(|_| ApiError::bad_request("signature must be valid hex"))?,
+        nonce: body.nonce,
+    };
+
+    let result = ledger.create_transfer(Request::new(transfer)).await?.into_inner();
+
+    match result.error {
+        None => Ok((StatusCode::OK, Json(TransferResultBody { error: None }))),
+        Some(err) => {
+            let status = transfer_error_status(&err);
+            let code = transfer_error_code_name(err.code);
+            Ok((status, Json(TransferResultBody { error: Some(TransferErrorBody { code, message: err.message }) })))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ActionBody {
+    r#type: String,
+    timestamp: u64,
+    from: String,
+    to: String,
+    sum: u64,
+}
+
+impl From<Action> for ActionBody {
+    fn from(action: Action) -> Self {
+        let action_type = ActionType::try_from(action.r#type).unwrap_or(ActionType::Transfer);
+        Self {
+            r#type: format!("{action_type:?}"),
+            timestamp: action.timestamp,
+            from: hex::encode(&action.from),
+            to: hex::encode(&action.to),
+            sum: action.sum,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<u64>,
+}
+
+async fn get_history(
+    State(ledger): State<Ledger>,
+    Path(id): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<ActionBody>>, ApiError> {
+    let id = decode_id(&id)?;
+    let resp = ledger
+        .get_history(Request::new(GetHistoryRequest { id, limit: query.limit.unwrap_or(u64::MAX) }))
+        .await?
+        .into_inner();
+    Ok(Json(resp.actions.into_iter().map(ActionBody::from).collect()))
+}
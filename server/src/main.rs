@@ -1,31 +1,63 @@
-use std::{collections::HashMap, sync::Arc};
+mod http;
+mod store;
+
+use std::{net::SocketAddr, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
 use async_trait::async_trait;
 use protos::{
-    ledger_server::LedgerServer, Account, CreateAccountReq, CreateAccountResponse, GetAccountReq,
+    ledger_server::LedgerServer, Account, Action, CreateAccountReq, CreateAccountResponse, GetAccountReq,
     Transfer, TransferResult, TransferError, transfer_error, GetHistoryRequest, GetHistoryResponse,
     FreezeAccountRequest, FreezeAccountResponse, UnfreezeAccountRequest, UnfreezeAccountResponse
 };
-use tokio::sync::Mutex;
+use protos::action::ActionType;
 use tonic::{transport::Server, Status, Request, Response};
 use rand::rngs::OsRng;
 use secp256k1::{Secp256k1, PublicKey, Message, ecdsa::Signature};
 use sha2::{Sha256, Digest};
 
+use store::{CommitError, InMemoryStore, LedgerStore, RedisStore, TransferVerifier};
+
+/// Width of the rolling window over which a `limit` is enforced.
+const TRANSFER_LIMIT_WINDOW_SECS: u64 = 24 * 60 * 60;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let server = tokio::spawn(
-        Server::builder()
-            .add_service(LedgerServer::new(Ledger::default()))
-            .serve("[::]:50051".parse()?),
-    );
-    println!("Listening on [::]:50051");
-    server.await??;
+    let store: Arc<dyn LedgerStore> = match std::env::var("LEDGER_BACKEND").as_deref() {
+        Ok("redis") => {
+            let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+            Arc::new(RedisStore::connect(&redis_url).await?)
+        }
+        _ => Arc::new(InMemoryStore::default()),
+    };
+    let ledger = Ledger::new(store);
+
+    let grpc_addr = "[::]:50051".parse()?;
+    let http_addr: SocketAddr = std::env::var("HTTP_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
+        .parse()?;
+
+    println!("Listening on {grpc_addr} (gRPC) and {http_addr} (HTTP)");
+
+    let grpc = Server::builder()
+        .add_service(LedgerServer::new(ledger.clone()))
+        .serve(grpc_addr);
+
+    tokio::try_join!(
+        async { grpc.await.map_err(|e| Box::<dyn std::error::Error>::from(e)) },
+        async { http::serve(ledger, http_addr).await.map_err(|e| Box::<dyn std::error::Error>::from(e)) },
+    )?;
+
     Ok(())
 }
 
-#[derive(Default, Clone)]
-struct Ledger {
-    accounts: Arc<Mutex<HashMap<Vec<u8>, Account>>>,
+#[derive(Clone)]
+pub(crate) struct Ledger {
+    store: Arc<dyn LedgerStore>,
+}
+
+impl Ledger {
+    fn new(store: Arc<dyn LedgerStore>) -> Self {
+        Self { store }
+    }
 }
 
 #[async_trait]
@@ -34,18 +66,19 @@ impl protos::ledger_server::Ledger for Ledger {
         &self,
         request: Request<FreezeAccountRequest>,
     ) -> Result<Response<FreezeAccountResponse>, Status> {
-        let request = request.into_inner();
-        let id = request.id;
-        let mut accounts = self.accounts.lock().await;
-        let account = accounts.get_mut(&id).ok_or_else(|| Status::not_found("Account not found"))?;
+        let id = request.into_inner().id;
+        let account = self.store.get_account(&id).await.ok_or_else(|| Status::not_found("Account not found"))?;
         if account.is_frozen {
-            return Ok(tonic::Response::new(protos::FreezeAccountResponse {
+            return Ok(Response::new(FreezeAccountResponse {
                 success: false,
                 message: "Account is already frozen".to_string(),
             }));
         }
-        account.is_frozen = true;
-        Ok(tonic::Response::new(protos::FreezeAccountResponse {
+
+        self.store.set_frozen(&id, true).await.ok_or_else(|| Status::not_found("Account not found"))?;
+        self.store.append_action(&id, Self::build_action(ActionType::FreezeAccount, &id, &id, 0)).await;
+
+        Ok(Response::new(FreezeAccountResponse {
             success: true,
             message: "Account has been frozen".to_string(),
         }))
@@ -55,18 +88,19 @@ impl protos::ledger_server::Ledger for Ledger {
         &self,
         request: Request<UnfreezeAccountRequest>,
     ) -> Result<Response<UnfreezeAccountResponse>, Status> {
-        let request = request.into_inner();
-        let id = request.id;
-        let mut accounts = self.accounts.lock().await;
-        let account = accounts.get_mut(&id).ok_or_else(|| Status::not_found("Account not found"))?;
+        let id = request.into_inner().id;
+        let account = self.store.get_account(&id).await.ok_or_else(|| Status::not_found("Account not found"))?;
         if !account.is_frozen {
-            return Ok(tonic::Response::new(protos::UnfreezeAccountResponse {
+            return Ok(Response::new(UnfreezeAccountResponse {
                 success: false,
                 message: "Account is not frozen".to_string(),
             }));
         }
-        account.is_frozen = false;
-        Ok(tonic::Response::new(protos::UnfreezeAccountResponse {
+
+        self.store.set_frozen(&id, false).await.ok_or_else(|| Status::not_found("Account not found"))?;
+        self.store.append_action(&id, Self::build_action(ActionType::UnfreezeAccount, &id, &id, 0)).await;
+
+        Ok(Response::new(UnfreezeAccountResponse {
             success: true,
             message: "Account has been unfrozen".to_string(),
         }))
@@ -86,13 +120,15 @@ impl protos::ledger_server::Ledger for Ledger {
             name: req.name,
             balance: req.balance,
             is_frozen: false,
+            decimals: req.decimals,
+            asset: req.asset,
+            limit: req.limit,
         };
 
-        let mut accounts = self.accounts.lock().await;
-        accounts.insert(
-            account.id.clone(), 
-            account.clone()
-        );
+        self.store.put_account(account.clone()).await;
+        self.store
+            .append_action(&account.id, Self::build_action(ActionType::CreateAccount, &account.id, &account.id, account.balance))
+            .await;
 
         Ok(Response::new(CreateAccountResponse {
             account: Some(account),
@@ -105,11 +141,9 @@ impl protos::ledger_server::Ledger for Ledger {
         request: Request<GetAccountReq>,
     ) -> Result<Response<Account>, Status> {
         let req = request.into_inner();
-        let accounts = self.accounts.lock().await;
-
-        accounts
-            .get(&req.id)
-            .cloned()
+        self.store
+            .get_account(&req.id)
+            .await
             .ok_or_else(|| Status::not_found("Account not found"))
             .map(Response::new)
     }
@@ -121,37 +155,47 @@ impl protos::ledger_server::Ledger for Ledger {
         let transfer = request.into_inner();
 
         let message_hash = self.create_transfer_message_hash(&transfer)?;
-        let from_account = self.get_account(Request::new(GetAccountReq { id: transfer.from_account.clone() })).await?.into_inner();
-        let to_account = self.get_account(Request::new(GetAccountReq { id: transfer.to_account.clone() })).await?.into_inner();
-
-        match self.verify_transfer_conditions(&transfer, &from_account, &to_account, &message_hash) {
-            Ok(_) => {},
-            Err(err) => return Ok(Response::new(TransferResult{
-                error: Some(err)
-            }))
-        };
 
-        let accounts = self.accounts.lock().await;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let window_start = now.saturating_sub(TRANSFER_LIMIT_WINDOW_SECS);
 
-        match self.update_balances(&transfer, accounts, &from_account, &to_account) {
-            Ok(_) => {},
-            Err(err) => return Ok(Response::new(TransferResult {
-                error: Some(err)
-            }))
-        }
+        let verify_transfer = transfer.clone();
+        let verify: TransferVerifier =
+            Box::new(move |from_account, to_account, nonce_already_used, recent_outflow| {
+                Self::verify_transfer_conditions(
+                    &verify_transfer,
+                    from_account,
+                    to_account,
+                    &message_hash,
+                    nonce_already_used,
+                    recent_outflow,
+                )
+            });
 
-        Ok(Response::new(TransferResult {
-            error: None,
-        }))
+        let actions = [
+            Self::build_action(ActionType::Transfer, &transfer.from_account, &transfer.to_account, transfer.amount),
+            Self::build_action(ActionType::Transfer, &transfer.from_account, &transfer.to_account, transfer.amount),
+        ];
+
+        match self.store.commit_transfer(&transfer, window_start, verify, actions).await {
+            Ok(()) => Ok(Response::new(TransferResult { error: None })),
+            Err(CommitError::Rejected(err)) => Ok(Response::new(TransferResult { error: Some(err) })),
+            Err(CommitError::Unavailable(message)) => Err(Status::unavailable(message)),
+        }
     }
 
     async fn get_history(
-        &self, 
+        &self,
         request: Request<GetHistoryRequest>
     ) -> Result<Response<GetHistoryResponse>, Status> {
-        Ok(Response::new(GetHistoryResponse {
-            actions: vec![],
-        }))
+        let req = request.into_inner();
+
+        if self.store.get_account(&req.id).await.is_none() {
+            return Err(Status::not_found("Account not found"));
+        }
+
+        let actions = self.store.get_history(&req.id, req.limit).await;
+        Ok(Response::new(GetHistoryResponse { actions }))
     }
 }
 
@@ -161,98 +205,200 @@ impl Ledger {
         message.extend_from_slice(&transfer.from_account);
         message.extend_from_slice(&transfer.to_account);
         message.extend_from_slice(&transfer.amount.to_le_bytes());
+        message.extend_from_slice(&transfer.nonce.to_le_bytes());
         Ok(Sha256::digest(&message).into())
     }
 
     fn verify_transfer_conditions(
-        &self,
         transfer: &Transfer,
         from_account: &Account,
         to_account: &Account,
         message_hash: &[u8; 32],
+        nonce_already_used: bool,
+        recent_outflow: u64,
     ) -> Result<(), TransferError> {
-        if from_account.is_frozen {
-            return Err(TransferError {
-                code: transfer_error::Code::FrozenAccount.into(),
-                message: "From account is frozen".to_string(),
-            });
-        }
-    
-        if to_account.is_frozen {
-            return Err(TransferError {
-                code: transfer_error::Code::FrozenAccount.into(),
-                message: "To account is frozen".to_string(),
-            });
-        }
-    
+        // Signature verification comes first: without it the caller hasn't
+        // proven they control the account, so none of the checks below
+        // (frozen/nonce/limit) should run, or an unauthenticated request
+        // could use their outcome to probe account policy it isn't entitled
+        // to see.
         let public_key = PublicKey::from_slice(&from_account.id)
             .map_err(|_| TransferError {
                 code: transfer_error::Code::InvalidSignature.into(),
                 message: "Invalid public key".to_string(),
             })?;
-    
+
         let secp = Secp256k1::verification_only();
         let secp_message = Message::from_slice(message_hash)
             .map_err(|_| TransferError {
                 code: transfer_error::Code::InvalidSignature.into(),
                 message: "Invalid message".to_string(),
             })?;
-    
+
         let signature = Signature::from_compact(&transfer.signature)
             .map_err(|_| TransferError {
                 code: transfer_error::Code::InvalidSignature.into(),
                 message: "Invalid signature format".to_string(),
             })?;
-    
+
         secp.verify_ecdsa(&secp_message, &signature, &public_key)
             .map_err(|_| TransferError {
                 code: transfer_error::Code::InvalidSignature.into(),
                 message: "Invalid signature".to_string(),
             })?;
-    
-        Ok(())
-    }
 
-    fn update_balances(
-        &self,
-        transfer: &Transfer,
-        mut accounts: tokio::sync::MutexGuard<HashMap<Vec<u8>, Account>>,
-        from_account: &Account,
-        to_account: &Account,
-    ) -> Result<(), TransferError> {
-        let new_from_balance = from_account.balance.checked_sub(transfer.amount).ok_or_else(|| {
-            TransferError {
-                code: transfer_error::Code::InsufficientBalance.into(),
-                message: "Insufficient balance".to_string(),
-            }
-        })?;
-        let new_to_balance = to_account.balance.checked_add(transfer.amount).ok_or_else(|| {
-            TransferError{
-                code: transfer_error::Code::BalanceOverflow.into(),
-                message: "Balance overflow".to_string()
+        if from_account.is_frozen {
+            return Err(TransferError {
+                code: transfer_error::Code::FrozenAccount.into(),
+                message: "From account is frozen".to_string(),
+            });
+        }
+
+        if to_account.is_frozen {
+            return Err(TransferError {
+                code: transfer_error::Code::FrozenAccount.into(),
+                message: "To account is frozen".to_string(),
+            });
+        }
+
+        if nonce_already_used {
+            return Err(TransferError {
+                code: transfer_error::Code::ReplayedNonce.into(),
+                message: "Transfer nonce has already been used".to_string(),
+            });
+        }
+
+        if from_account.limit > 0 {
+            // `checked_pow`/`checked_mul` return `None` on overflow (e.g.
+            // `decimals >= 20`); treat that as "limit can't be represented,
+            // so nothing clears it" rather than silently skipping the check.
+            let scaled_limit = 10u64
+                .checked_pow(from_account.decimals)
+                .and_then(|factor| from_account.limit.checked_mul(factor));
+
+            let within_limit = match scaled_limit {
+                Some(scaled_limit) => recent_outflow.saturating_add(transfer.amount) <= scaled_limit,
+                None => false,
+            };
+
+            if !within_limit {
+                return Err(TransferError {
+                    code: transfer_error::Code::LimitExceeded.into(),
+                    message: "Transfer would exceed the account's rolling withdrawal limit".to_string(),
+                });
             }
-        })?;
-
-        let from_account = accounts
-            .get_mut(&transfer.from_account)
-            .ok_or_else(|| {
-                TransferError {
-                    code: transfer_error::Code::AccountNotFound.into(),
-                    message: "From account not found".to_string(),
-                }
-            })?;
-        from_account.balance = new_from_balance;
-
-        let to_account = accounts
-            .get_mut(&transfer.to_account)
-            .ok_or_else(|| {
-                TransferError {
-                    code: transfer_error::Code::AccountNotFound.into(),
-                    message: "To account not found".to_string(),
-                }
-            })?;
-        to_account.balance = new_to_balance;
+        }
 
         Ok(())
     }
+
+    fn build_action(action_type: ActionType, from: &[u8], to: &[u8], sum: u64) -> Action {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Action {
+            r#type: action_type.into(),
+            timestamp,
+            from: from.to_vec(),
+            to: to.to_vec(),
+            sum,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(id: Vec<u8>, decimals: u32, limit: u64) -> Account {
+        Account {
+            id,
+            name: "test".to_string(),
+            balance: 1_000_000,
+            is_frozen: false,
+            decimals,
+            asset: String::new(),
+            limit,
+        }
+    }
+
+    // Signature verification now runs before the limit/nonce checks, so
+    // exercising those checks needs a transfer that actually verifies: a real
+    // keypair, signing the same message the server hashes in
+    // `create_transfer_message_hash`.
+    fn signed_transfer(amount: u64) -> (Transfer, [u8; 32]) {
+        let secp = Secp256k1::new();
+        let (secret_key, public_key) = secp.generate_keypair(&mut OsRng);
+        let from_account = public_key.serialize().to_vec();
+        let to_account = vec![2];
+        let nonce = 1;
+
+        let mut message = Vec::new();
+        message.extend_from_slice(&from_account);
+        message.extend_from_slice(&to_account);
+        message.extend_from_slice(&amount.to_le_bytes());
+        message.extend_from_slice(&nonce.to_le_bytes());
+        let hash: [u8; 32] = Sha256::digest(&message).into();
+
+        let signature = secp.sign_ecdsa(&Message::from_slice(&hash).unwrap(), &secret_key);
+
+        (
+            Transfer { from_account, to_account, amount, signature: signature.serialize_compact().to_vec(), nonce },
+            hash,
+        )
+    }
+
+    #[test]
+    fn limit_is_scaled_by_10_to_the_decimals() {
+        let (transfer, hash) = signed_transfer(1);
+        let from = account(transfer.from_account.clone(), 2, 5); // limit of 5 whole units at 2 decimals = 500 base units
+        let to = account(transfer.to_account.clone(), 0, 0);
+
+        let err = Ledger::verify_transfer_conditions(&transfer, &from, &to, &hash, false, 500).unwrap_err();
+        assert_eq!(err.code, transfer_error::Code::LimitExceeded as i32);
+    }
+
+    #[test]
+    fn transfers_within_the_scaled_limit_clear_the_limit_check() {
+        let (transfer, hash) = signed_transfer(1);
+        let from = account(transfer.from_account.clone(), 2, 5);
+        let to = account(transfer.to_account.clone(), 0, 0);
+
+        // Within the 500-base-unit limit, so the limit check (and every
+        // check before it) passes.
+        assert!(Ledger::verify_transfer_conditions(&transfer, &from, &to, &hash, false, 499).is_ok());
+    }
+
+    #[test]
+    fn limit_fails_closed_when_scaling_overflows() {
+        let (transfer, hash) = signed_transfer(1);
+        let from = account(transfer.from_account.clone(), 20, 1); // 10^20 overflows u64, so the limit can't be represented
+        let to = account(transfer.to_account.clone(), 0, 0);
+
+        let err = Ledger::verify_transfer_conditions(&transfer, &from, &to, &hash, false, 0).unwrap_err();
+        assert_eq!(err.code, transfer_error::Code::LimitExceeded as i32);
+    }
+
+    #[test]
+    fn zero_limit_means_unlimited() {
+        let (transfer, hash) = signed_transfer(u64::MAX);
+        let from = account(transfer.from_account.clone(), 0, 0);
+        let to = account(transfer.to_account.clone(), 0, 0);
+
+        assert!(Ledger::verify_transfer_conditions(&transfer, &from, &to, &hash, false, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn signature_is_verified_before_business_checks() {
+        let (mut transfer, hash) = signed_transfer(1);
+        transfer.signature = vec![0u8; 64];
+        let from = account(transfer.from_account.clone(), 0, 0);
+        let mut to = account(transfer.to_account.clone(), 0, 0);
+        to.is_frozen = true; // would also fail, but the bad signature must win
+
+        let err = Ledger::verify_transfer_conditions(&transfer, &from, &to, &hash, true, u64::MAX).unwrap_err();
+        assert_eq!(err.code, transfer_error::Code::InvalidSignature as i32);
+    }
 }
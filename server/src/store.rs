@@ -0,0 +1,680 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use protos::action::ActionType;
+use protos::{transfer_error, Account, Action, TransferError, Transfer};
+use tokio::sync::Mutex;
+
+/// Closure that inspects the live `from`/`to` accounts plus freshly-read
+/// nonce/limit state and decides whether a transfer may proceed. Passed into
+/// `commit_transfer` so verification runs against the same state that gets
+/// committed, instead of a snapshot taken before the lock was acquired.
+///
+/// `FnMut`, not `FnOnce`: an optimistic-locking backend (`RedisStore`) may
+/// need to re-run it against a freshly-read state on each retry of the same
+/// transaction.
+pub type TransferVerifier = Box<dyn FnMut(&Account, &Account, bool, u64) -> Result<(), TransferError> + Send>;
+
+/// Outcome of a failed `commit_transfer`: either a business rejection (bad
+/// signature, frozen account, limit exceeded, ...) that the caller reports
+/// back as a normal `TransferResult.error`, or an infrastructure failure
+/// (store unreachable, connection dropped, retry budget exhausted) that
+/// isn't a ledger decision at all and must not be disguised as one.
+pub enum CommitError {
+    Rejected(TransferError),
+    Unavailable(String),
+}
+
+impl From<TransferError> for CommitError {
+    fn from(err: TransferError) -> Self {
+        CommitError::Rejected(err)
+    }
+}
+
+/// Storage backend for ledger state. `Ledger` only ever talks to the store
+/// through this trait, so it doesn't care whether accounts live in a
+/// `HashMap` or in Redis.
+#[async_trait]
+pub trait LedgerStore: Send + Sync {
+    async fn get_account(&self, id: &[u8]) -> Option<Account>;
+
+    async fn put_account(&self, account: Account);
+
+    /// Flips the frozen flag and returns the updated account, or `None` if
+    /// the account doesn't exist.
+    async fn set_frozen(&self, id: &[u8], frozen: bool) -> Option<Account>;
+
+    /// Looks up the live `from`/`to` accounts, runs `verify` against them
+    /// under the same lock (or store transaction), and only on success
+    /// debits/credits the balances, consumes `transfer.nonce`, and appends
+    /// `actions` (`[from_entry, to_entry]`) to history. `limit_window_start`
+    /// bounds the rolling-outflow figure `verify` is given. Nothing about
+    /// the transfer is observable or committed half-done: a concurrent
+    /// transfer from the same account either fully precedes or fully
+    /// follows this one.
+    async fn commit_transfer(
+        &self,
+        transfer: &Transfer,
+        limit_window_start: u64,
+        verify: TransferVerifier,
+        actions: [Action; 2],
+    ) -> Result<(), CommitError>;
+
+    async fn append_action(&self, account_id: &[u8], action: Action);
+
+    /// Returns up to `limit` entries for `account_id`, most recent first.
+    async fn get_history(&self, account_id: &[u8], limit: u64) -> Vec<Action>;
+}
+
+fn sum_outflow(entries: &[Action], account_id: &[u8], since: u64) -> u64 {
+    entries
+        .iter()
+        .filter(|action| action.r#type == ActionType::Transfer as i32 && action.from == account_id && action.timestamp >= since)
+        .map(|action| action.sum)
+        .sum()
+}
+
+/// The original in-process behavior: everything lives in a `Mutex`-guarded
+/// `HashMap` and is lost on restart.
+#[derive(Default)]
+pub struct InMemoryStore {
+    accounts: Mutex<HashMap<Vec<u8>, Account>>,
+    history: Mutex<HashMap<Vec<u8>, Vec<Action>>>,
+    nonces: Mutex<HashMap<Vec<u8>, HashSet<u64>>>,
+}
+
+#[async_trait]
+impl LedgerStore for InMemoryStore {
+    async fn get_account(&self, id: &[u8]) -> Option<Account> {
+        self.accounts.lock().await.get(id).cloned()
+    }
+
+    async fn put_account(&self, account: Account) {
+        self.accounts.lock().await.insert(account.id.clone(), account);
+    }
+
+    async fn set_frozen(&self, id: &[u8], frozen: bool) -> Option<Account> {
+        let mut accounts = self.accounts.lock().await;
+        let account = accounts.get_mut(id)?;
+        account.is_frozen = frozen;
+        Some(account.clone())
+    }
+
+    async fn commit_transfer(
+        &self,
+        transfer: &Transfer,
+        limit_window_start: u64,
+        mut verify: TransferVerifier,
+        actions: [Action; 2],
+    ) -> Result<(), CommitError> {
+        // Held for the entire lookup + verify + commit: no other transfer
+        // touching these accounts can interleave.
+        let mut accounts = self.accounts.lock().await;
+        let mut nonces = self.nonces.lock().await;
+        let mut history = self.history.lock().await;
+
+        let from_account = accounts.get(&transfer.from_account).cloned().ok_or_else(|| TransferError {
+            code: transfer_error::Code::AccountNotFound.into(),
+            message: "From account not found".to_string(),
+        })?;
+        let to_account = accounts.get(&transfer.to_account).cloned().ok_or_else(|| TransferError {
+            code: transfer_error::Code::AccountNotFound.into(),
+            message: "To account not found".to_string(),
+        })?;
+
+        let nonce_already_used = nonces
+            .get(&transfer.from_account)
+            .is_some_and(|seen| seen.contains(&transfer.nonce));
+
+        let recent_outflow = history
+            .get(&transfer.from_account)
+            .map(|entries| sum_outflow(entries, &transfer.from_account, limit_window_start))
+            .unwrap_or_default();
+
+        verify(&from_account, &to_account, nonce_already_used, recent_outflow)?;
+
+        // A self-transfer's debit and credit land on the same account, so
+        // the credit must not also add `transfer.amount` back on top of the
+        // already-debited balance — that would mint funds out of nothing.
+        let is_self_transfer = transfer.from_account == transfer.to_account;
+
+        let new_from_balance = from_account.balance.checked_sub(transfer.amount).ok_or_else(|| TransferError {
+            code: transfer_error::Code::InsufficientBalance.into(),
+            message: "Insufficient balance".to_string(),
+        })?;
+        let new_to_balance = if is_self_transfer {
+            from_account.balance
+        } else {
+            to_account.balance.checked_add(transfer.amount).ok_or_else(|| TransferError {
+                code: transfer_error::Code::BalanceOverflow.into(),
+                message: "Balance overflow".to_string(),
+            })?
+        };
+
+        accounts.get_mut(&transfer.from_account).unwrap().balance = new_from_balance;
+        accounts.get_mut(&transfer.to_account).unwrap().balance = new_to_balance;
+
+        nonces.entry(transfer.from_account.clone()).or_default().insert(transfer.nonce);
+
+        let [from_action, to_action] = actions;
+        history.entry(transfer.from_account.clone()).or_default().push(from_action);
+        history.entry(transfer.to_account.clone()).or_default().push(to_action);
+
+        Ok(())
+    }
+
+    async fn append_action(&self, account_id: &[u8], action: Action) {
+        self.history
+            .lock()
+            .await
+            .entry(account_id.to_vec())
+            .or_default()
+            .push(action);
+    }
+
+    async fn get_history(&self, account_id: &[u8], limit: u64) -> Vec<Action> {
+        self.history
+            .lock()
+            .await
+            .get(account_id)
+            .map(|entries| entries.iter().rev().take(limit as usize).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Redis-backed store so the ledger survives restarts and can be shared by
+/// more than one server process. Accounts are kept as Redis hashes, history
+/// as append-only lists, and consumed nonces as sets. `commit_transfer` uses
+/// WATCH/MULTI/EXEC: it reads the live from/to accounts and nonce/outflow
+/// state, runs `verify` against them, then commits the debit, credit, nonce,
+/// and both history entries in one transaction, retrying if another process
+/// touched a watched key in between.
+///
+/// WATCH/MULTI are connection-scoped, so `commit_transfer` must not run them
+/// over `manager` (a multiplexed connection shared by every caller) — two
+/// concurrent transfers would interleave their WATCH/MULTI state on the same
+/// socket and silently lose the atomicity guarantee. It instead checks out
+/// one of `transaction_conns`, a small pool of dedicated connections
+/// reserved for WATCH/MULTI/EXEC use, so unrelated transfers can still run
+/// concurrently instead of being fully serialized through a single
+/// connection. A connection is dropped and lazily reopened if a command on
+/// it comes back with a connection-level error.
+const TRANSACTION_POOL_SIZE: usize = 8;
+
+/// Cap on WATCH/MULTI/EXEC retries within a single `commit_transfer` call. A
+/// watched key changing under us just means another transfer on the same
+/// account won the race, which normally clears within a retry or two; a hot
+/// account under sustained contention should surface a transient failure
+/// instead of looping forever.
+const COMMIT_RETRY_LIMIT: u32 = 10;
+
+pub struct RedisStore {
+    client: redis::Client,
+    manager: redis::aio::ConnectionManager,
+    transaction_conns: Vec<Mutex<Option<redis::aio::Connection>>>,
+}
+
+impl RedisStore {
+    pub async fn connect(redis_url: &str) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let manager = client.get_tokio_connection_manager().await?;
+        let transaction_conns = (0..TRANSACTION_POOL_SIZE).map(|_| Mutex::new(None)).collect();
+        Ok(Self { client, manager, transaction_conns })
+    }
+
+    fn conn(&self) -> redis::aio::ConnectionManager {
+        self.manager.clone()
+    }
+
+    /// Picks a pool slot for a transaction touching `account_id`. Any free
+    /// slot would be correct; this just spreads unrelated transfers across
+    /// the pool instead of funneling every transfer through one connection.
+    fn transaction_slot(&self, account_id: &[u8]) -> &Mutex<Option<redis::aio::Connection>> {
+        let index = account_id.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) as usize % self.transaction_conns.len();
+        &self.transaction_conns[index]
+    }
+
+    fn account_key(id: &[u8]) -> String {
+        format!("m10:account:{}", hex::encode(id))
+    }
+
+    fn history_key(id: &[u8]) -> String {
+        format!("m10:history:{}", hex::encode(id))
+    }
+
+    fn nonces_key(id: &[u8]) -> String {
+        format!("m10:nonces:{}", hex::encode(id))
+    }
+
+    fn encode_action(action: &Action) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            action.r#type,
+            action.timestamp,
+            hex::encode(&action.from),
+            hex::encode(&action.to),
+            action.sum,
+        )
+    }
+
+    fn decode_action(entry: &str) -> Option<Action> {
+        let mut parts = entry.splitn(5, ':');
+        Some(Action {
+            r#type: parts.next()?.parse().ok()?,
+            timestamp: parts.next()?.parse().ok()?,
+            from: hex::decode(parts.next()?).ok()?,
+            to: hex::decode(parts.next()?).ok()?,
+            sum: parts.next()?.parse().ok()?,
+        })
+    }
+
+    /// `Ok(None)` means the account genuinely doesn't exist; `Err` means the
+    /// read itself failed (connection dropped, etc.). `commit_transfer`
+    /// reports an `Err` as an infrastructure failure rather than "not
+    /// found"; `get_account` and `set_frozen` fold it into `None`.
+    async fn read_account<C: redis::aio::ConnectionLike + Send>(
+        conn: &mut C,
+        id: &[u8],
+    ) -> redis::RedisResult<Option<Account>> {
+        let fields: HashMap<String, String> =
+            redis::cmd("HGETALL").arg(Self::account_key(id)).query_async(conn).await?;
+
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Account {
+            id: id.to_vec(),
+            name: fields.get("name").cloned().unwrap_or_default(),
+            balance: fields.get("balance").and_then(|v| v.parse().ok()).unwrap_or_default(),
+            is_frozen: fields.get("is_frozen").map(|v| v == "1").unwrap_or_default(),
+            decimals: fields.get("decimals").and_then(|v| v.parse().ok()).unwrap_or_default(),
+            asset: fields.get("asset").cloned().unwrap_or_default(),
+            limit: fields.get("limit").and_then(|v| v.parse().ok()).unwrap_or_default(),
+        }))
+    }
+}
+
+#[async_trait]
+impl LedgerStore for RedisStore {
+    async fn get_account(&self, id: &[u8]) -> Option<Account> {
+        let mut conn = self.conn();
+        // The shared, auto-reconnecting `ConnectionManager` is used here, not
+        // the transaction pool, so a read error is no worse than "treat it
+        // like not found" — the next call gets a fresh attempt either way.
+        Self::read_account(&mut conn, id).await.ok().flatten()
+    }
+
+    async fn put_account(&self, account: Account) {
+        let mut conn = self.conn();
+        let _: redis::RedisResult<()> = redis::cmd("HSET")
+            .arg(Self::account_key(&account.id))
+            .arg("name")
+            .arg(&account.name)
+            .arg("balance")
+            .arg(account.balance)
+            .arg("is_frozen")
+            .arg(if account.is_frozen { 1 } else { 0 })
+            .arg("decimals")
+            .arg(account.decimals)
+            .arg("asset")
+            .arg(&account.asset)
+            .arg("limit")
+            .arg(account.limit)
+            .query_async(&mut conn)
+            .await;
+    }
+
+    async fn set_frozen(&self, id: &[u8], frozen: bool) -> Option<Account> {
+        let mut conn = self.conn();
+        // HSET on an unknown id would create a partial account hash.
+        Self::read_account(&mut conn, id).await.ok().flatten()?;
+        let _: redis::RedisResult<()> = redis::cmd("HSET")
+            .arg(Self::account_key(id))
+            .arg("is_frozen")
+            .arg(if frozen { 1 } else { 0 })
+            .query_async(&mut conn)
+            .await
+            .ok()?;
+        Self::read_account(&mut conn, id).await.ok().flatten()
+    }
+
+    async fn commit_transfer(
+        &self,
+        transfer: &Transfer,
+        limit_window_start: u64,
+        mut verify: TransferVerifier,
+        actions: [Action; 2],
+    ) -> Result<(), CommitError> {
+        let from_key = Self::account_key(&transfer.from_account);
+        let to_key = Self::account_key(&transfer.to_account);
+        let nonce_key = Self::nonces_key(&transfer.from_account);
+        let history_from_key = Self::history_key(&transfer.from_account);
+        let history_to_key = Self::history_key(&transfer.to_account);
+        let [from_action, to_action] = actions;
+
+        // Checked out from the transaction pool: WATCH/MULTI are
+        // connection-scoped, so two concurrent transfers sharing a
+        // connection would clobber each other's watch state. If a command
+        // below comes back with a connection-level error we drop the cached
+        // connection so the next call on this slot reconnects, instead of
+        // reopening a fresh TCP connection on every transfer.
+        let mut guard = self.transaction_slot(&transfer.from_account).lock().await;
+
+        let mut retries_left = COMMIT_RETRY_LIMIT;
+        loop {
+            if guard.is_none() {
+                let conn = self.client.get_async_connection().await.map_err(Self::redis_transfer_error)?;
+                *guard = Some(conn);
+            }
+            let conn = guard.as_mut().expect("connection just ensured present");
+
+            // `history_from_key` is watched too: it's the source of
+            // `recent_outflow` below, and an unwatched read of it would let
+            // two concurrent transfers both pass the rolling-limit check
+            // against the same stale outflow figure.
+            let watch_result: redis::RedisResult<()> = redis::cmd("WATCH")
+                .arg(&from_key)
+                .arg(&to_key)
+                .arg(&nonce_key)
+                .arg(&history_from_key)
+                .query_async(conn)
+                .await;
+            if let Err(err) = watch_result {
+                *guard = None;
+                return Err(Self::redis_transfer_error(err));
+            }
+            let conn = guard.as_mut().expect("connection just ensured present");
+
+            let from_account = Self::read_account(conn, &transfer.from_account).await;
+            let to_account = Self::read_account(conn, &transfer.to_account).await;
+
+            let (from_account, to_account) = match (from_account, to_account) {
+                (Ok(from_account), Ok(to_account)) => (from_account, to_account),
+                (from, to) => {
+                    // A genuine read error, not just a missing key: the
+                    // connection may be wedged, so drop it rather than
+                    // caching a socket future transfers would inherit.
+                    *guard = None;
+                    return Err(Self::redis_transfer_error(
+                        from.err().or(to.err()).expect("one side errored"),
+                    ));
+                }
+            };
+            let (from_account, to_account) = match (from_account, to_account) {
+                (Some(from_account), Some(to_account)) => (from_account, to_account),
+                (from, _) => {
+                    Self::unwatch(&mut guard).await;
+                    let message = if from.is_none() { "From account not found" } else { "To account not found" };
+                    return Err(CommitError::Rejected(TransferError {
+                        code: transfer_error::Code::AccountNotFound.into(),
+                        message: message.to_string(),
+                    }));
+                }
+            };
+            let conn = guard.as_mut().expect("connection just ensured present");
+
+            let nonce_already_used: bool = match redis::cmd("SISMEMBER")
+                .arg(&nonce_key)
+                .arg(transfer.nonce)
+                .query_async(conn)
+                .await
+            {
+                Ok(used) => used,
+                Err(err) => {
+                    *guard = None;
+                    return Err(Self::redis_transfer_error(err));
+                }
+            };
+            let conn = guard.as_mut().expect("connection just ensured present");
+
+            let history_entries: Vec<String> = match redis::cmd("LRANGE")
+                .arg(&history_from_key)
+                .arg(0)
+                .arg(-1)
+                .query_async(conn)
+                .await
+            {
+                Ok(entries) => entries,
+                Err(err) => {
+                    *guard = None;
+                    return Err(Self::redis_transfer_error(err));
+                }
+            };
+            let recent_outflow = sum_outflow(
+                &history_entries.iter().filter_map(|entry| Self::decode_action(entry)).collect::<Vec<_>>(),
+                &transfer.from_account,
+                limit_window_start,
+            );
+            let conn = guard.as_mut().expect("connection just ensured present");
+
+            if let Err(err) = verify(&from_account, &to_account, nonce_already_used, recent_outflow) {
+                Self::unwatch(&mut guard).await;
+                return Err(CommitError::Rejected(err));
+            }
+
+            // A self-transfer's debit and credit land on the same Redis
+            // key, so the credit must not also add `transfer.amount` back
+            // on top of the already-debited balance — that would mint funds
+            // out of nothing when the second HSET overwrites the first.
+            let is_self_transfer = transfer.from_account == transfer.to_account;
+
+            let new_from_balance = match from_account.balance.checked_sub(transfer.amount) {
+                Some(balance) => balance,
+                None => {
+                    Self::unwatch(&mut guard).await;
+                    return Err(CommitError::Rejected(TransferError {
+                        code: transfer_error::Code::InsufficientBalance.into(),
+                        message: "Insufficient balance".to_string(),
+                    }));
+                }
+            };
+            let new_to_balance = if is_self_transfer {
+                from_account.balance
+            } else {
+                match to_account.balance.checked_add(transfer.amount) {
+                    Some(balance) => balance,
+                    None => {
+                        Self::unwatch(&mut guard).await;
+                        return Err(CommitError::Rejected(TransferError {
+                            code: transfer_error::Code::BalanceOverflow.into(),
+                            message: "Balance overflow".to_string(),
+                        }));
+                    }
+                }
+            };
+
+            let result: redis::RedisResult<Option<()>> = redis::pipe()
+                .atomic()
+                .cmd("HSET").arg(&from_key).arg("balance").arg(new_from_balance).ignore()
+                .cmd("HSET").arg(&to_key).arg("balance").arg(new_to_balance).ignore()
+                .cmd("SADD").arg(&nonce_key).arg(transfer.nonce).ignore()
+                .cmd("RPUSH").arg(&history_from_key).arg(Self::encode_action(&from_action)).ignore()
+                .cmd("RPUSH").arg(&history_to_key).arg(Self::encode_action(&to_action)).ignore()
+                .query_async(conn)
+                .await;
+
+            match result {
+                Ok(Some(())) => return Ok(()),
+                Ok(None) => {
+                    // a watched key changed under us; retry against fresh state
+                    if retries_left == 0 {
+                        return Err(CommitError::Unavailable(format!(
+                            "commit_transfer gave up after {COMMIT_RETRY_LIMIT} retries under contention"
+                        )));
+                    }
+                    retries_left -= 1;
+                    continue;
+                }
+                Err(err) => {
+                    *guard = None;
+                    return Err(Self::redis_transfer_error(err));
+                }
+            }
+        }
+    }
+
+    async fn append_action(&self, account_id: &[u8], action: Action) {
+        let mut conn = self.conn();
+        let _: redis::RedisResult<()> = redis::cmd("RPUSH")
+            .arg(Self::history_key(account_id))
+            .arg(Self::encode_action(&action))
+            .query_async(&mut conn)
+            .await;
+    }
+
+    async fn get_history(&self, account_id: &[u8], limit: u64) -> Vec<Action> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let mut conn = self.conn();
+        let start = -(limit.min(i64::MAX as u64) as i64);
+        let entries: Vec<String> = redis::cmd("LRANGE")
+            .arg(Self::history_key(account_id))
+            .arg(start)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await
+            .unwrap_or_default();
+
+        entries.iter().rev().filter_map(|entry| Self::decode_action(entry)).collect()
+    }
+}
+
+impl RedisStore {
+    /// Infrastructure failures (dropped connection, failed WATCH/read/EXEC)
+    /// are never a ledger decision, so they don't get a `TransferError` code
+    /// — the caller surfaces them on the transport's own error channel
+    /// instead (`Status::unavailable` over gRPC).
+    fn redis_transfer_error(err: redis::RedisError) -> CommitError {
+        CommitError::Unavailable(format!("redis transaction failed: {err}"))
+    }
+
+    /// Best-effort UNWATCH on an early-exit path. If even that fails, the
+    /// connection is in an unknown state, so drop it from the pool slot
+    /// rather than let the next transfer on this slot inherit a dead socket.
+    async fn unwatch(guard: &mut Option<redis::aio::Connection>) {
+        let Some(conn) = guard.as_mut() else { return };
+        if redis::cmd("UNWATCH").query_async::<_, ()>(conn).await.is_err() {
+            *guard = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(id: u8, balance: u64) -> Account {
+        Account {
+            id: vec![id],
+            name: "test".to_string(),
+            balance,
+            is_frozen: false,
+            decimals: 0,
+            asset: String::new(),
+            limit: 0,
+        }
+    }
+
+    fn transfer(from: u8, to: u8, amount: u64, nonce: u64) -> Transfer {
+        Transfer { from_account: vec![from], to_account: vec![to], amount, signature: Vec::new(), nonce }
+    }
+
+    fn actions(from: u8, to: u8, amount: u64) -> [Action; 2] {
+        let action = |from: u8, to: u8| Action {
+            r#type: ActionType::Transfer as i32,
+            timestamp: 0,
+            from: vec![from],
+            to: vec![to],
+            sum: amount,
+        };
+        [action(from, to), action(from, to)]
+    }
+
+    fn always_ok() -> TransferVerifier {
+        Box::new(|_, _, _, _| Ok(()))
+    }
+
+    #[tokio::test]
+    async fn commit_transfer_moves_the_exact_balance_verified() {
+        let store = InMemoryStore::default();
+        store.put_account(account(1, 100)).await;
+        store.put_account(account(2, 0)).await;
+
+        store.commit_transfer(&transfer(1, 2, 40, 7), 0, always_ok(), actions(1, 2, 40)).await.unwrap();
+
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 60);
+        assert_eq!(store.get_account(&[2]).await.unwrap().balance, 40);
+    }
+
+    #[tokio::test]
+    async fn commit_transfer_does_not_consume_the_nonce_when_verify_rejects() {
+        let store = InMemoryStore::default();
+        store.put_account(account(1, 100)).await;
+        store.put_account(account(2, 0)).await;
+
+        let reject: TransferVerifier = Box::new(|_, _, _, _| {
+            Err(TransferError { code: transfer_error::Code::FrozenAccount.into(), message: "frozen".to_string() })
+        });
+        store.commit_transfer(&transfer(1, 2, 40, 7), 0, reject, actions(1, 2, 40)).await.unwrap_err();
+
+        // Balance is untouched, and a retry of the same nonce is still treated as unused.
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 100);
+        let retry: TransferVerifier = Box::new(|_, _, nonce_already_used, _| {
+            assert!(!nonce_already_used);
+            Ok(())
+        });
+        store.commit_transfer(&transfer(1, 2, 40, 7), 0, retry, actions(1, 2, 40)).await.unwrap();
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 60);
+    }
+
+    #[tokio::test]
+    async fn commit_transfer_rejects_a_replayed_nonce_without_double_spending() {
+        let store = InMemoryStore::default();
+        store.put_account(account(1, 100)).await;
+        store.put_account(account(2, 0)).await;
+
+        let transfer = transfer(1, 2, 40, 7);
+        store.commit_transfer(&transfer, 0, always_ok(), actions(1, 2, 40)).await.unwrap();
+
+        let reject_replay: TransferVerifier = Box::new(|_, _, nonce_already_used, _| {
+            if nonce_already_used {
+                Err(TransferError { code: transfer_error::Code::ReplayedNonce.into(), message: "replayed".to_string() })
+            } else {
+                Ok(())
+            }
+        });
+        store.commit_transfer(&transfer, 0, reject_replay, actions(1, 2, 40)).await.unwrap_err();
+
+        // The first transfer's debit is the only one that landed.
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 60);
+        assert_eq!(store.get_account(&[2]).await.unwrap().balance, 40);
+    }
+
+    #[tokio::test]
+    async fn commit_transfer_leaves_balances_untouched_on_insufficient_funds() {
+        let store = InMemoryStore::default();
+        store.put_account(account(1, 10)).await;
+        store.put_account(account(2, 0)).await;
+
+        let result = store.commit_transfer(&transfer(1, 2, 40, 7), 0, always_ok(), actions(1, 2, 40)).await;
+
+        assert!(matches!(
+            result,
+            Err(CommitError::Rejected(TransferError { code, .. })) if code == transfer_error::Code::InsufficientBalance as i32
+        ));
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 10);
+        assert_eq!(store.get_account(&[2]).await.unwrap().balance, 0);
+    }
+
+    #[tokio::test]
+    async fn commit_transfer_does_not_mint_funds_on_a_self_transfer() {
+        let store = InMemoryStore::default();
+        store.put_account(account(1, 100)).await;
+
+        store.commit_transfer(&transfer(1, 1, 40, 7), 0, always_ok(), actions(1, 1, 40)).await.unwrap();
+
+        assert_eq!(store.get_account(&[1]).await.unwrap().balance, 100);
+    }
+}